@@ -2,6 +2,31 @@
 #[derive(Debug)]
 pub struct BufferFullError<T>(T);
 
+/// Error returned when growing a buffer's output allocation fails.
+///
+/// Mirrors the shape of [`std::collections::TryReserveError`], which this is
+/// usually constructed from.
+#[derive(Debug)]
+pub enum BufferAllocError {
+    /// The requested capacity exceeds `isize::MAX` bytes.
+    CapacityOverflow,
+    /// The allocator reported an allocation failure.
+    AllocFailed,
+}
+
+impl From<std::collections::TryReserveError> for BufferAllocError {
+    fn from(err: std::collections::TryReserveError) -> Self {
+        // `TryReserveError::kind()` is not yet stable, so the overflow vs.
+        // allocator-failure distinction is read back off its `Display` text
+        // instead of the (unstable) `TryReserveErrorKind` enum.
+        if err.to_string().contains("exceeded") {
+            BufferAllocError::CapacityOverflow
+        } else {
+            BufferAllocError::AllocFailed
+        }
+    }
+}
+
 /// A buffer manages a fixed amount of data.
 ///
 /// The data are produced one at a time or consumed all at once.
@@ -9,15 +34,26 @@ pub trait Buffer<T> {
     /// Add the given element to the buffer if possible.
     fn add(&mut self, element: T) -> Result<(), BufferFullError<T>>;
 
-    /// Copy and return all elements of the buffer.
+    /// Copy and return all elements of the buffer, reporting an allocation
+    /// failure instead of aborting the process.
     ///
-    /// When this operation completes, the buffer state will reset so that the buffer
-    /// has no elements. In other words, the buffer elements are completely consumed
-    /// and ownership transferred.
+    /// When this operation completes successfully, the buffer state will
+    /// reset so that the buffer has no elements. In other words, the buffer
+    /// elements are completely consumed and ownership transferred. If the
+    /// output allocation fails, the buffer is left untouched so the data can
+    /// be retried later.
     ///
     /// Note that the underlying memory is *not* zeroed, so sensitive information should
     /// not be stored in this buffer.
-    fn consume(&mut self) -> Option<Vec<T>>;
+    fn try_consume(&mut self) -> Result<Option<Vec<T>>, BufferAllocError>;
+
+    /// Copy and return all elements of the buffer.
+    ///
+    /// This is a panicking convenience wrapper over [`Buffer::try_consume`]
+    /// for callers that would rather abort than handle allocation failure.
+    fn consume(&mut self) -> Option<Vec<T>> {
+        self.try_consume().expect("buffer allocation failed")
+    }
 
     /// Returns the number of currently elements in the buffer.
     fn used(&self) -> usize;
@@ -28,34 +64,190 @@ pub trait Buffer<T> {
 
 const BUFFER_SIZE: usize = 512;
 
-/// Buffer whose capacity is determined at compile time.
-pub struct StaticBuffer<T>
+/// Environment variable that overrides the default buffer capacity resolved
+/// by [`default_buffer_size`].
+const BUFFER_SIZE_ENV_VAR: &str = "DROPRATE_BUFFER_SIZE";
+
+/// Resolves the default buffer capacity from the `DROPRATE_BUFFER_SIZE`
+/// environment variable, falling back to `512` when it is unset or fails to
+/// parse as a `usize`.
+pub fn default_buffer_size() -> usize {
+    std::env::var(BUFFER_SIZE_ENV_VAR)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(BUFFER_SIZE)
+}
+
+/// Types that can overwrite themselves with a value the optimizer cannot
+/// elide, so sensitive data doesn't linger in memory after it is no longer
+/// needed.
+///
+/// Bounded by `Copy` (in addition to `Default`) so the blanket impl below
+/// only ever applies to types with no destructor to run: a volatile write
+/// overwrites bytes without dropping the value it replaces, which would leak
+/// e.g. a `String`'s heap allocation.
+pub trait Zeroize {
+    /// Overwrites `self` with its default value via a volatile write.
+    fn zeroize(&mut self);
+}
+
+impl<T: Copy + Default> Zeroize for T {
+    fn zeroize(&mut self) {
+        // SAFETY: `self` is a valid, initialized `T`, and a volatile write
+        // of another valid `T` is always sound; it just can't be elided.
+        // `T: Copy` means there's no destructor to skip by overwriting it.
+        unsafe {
+            std::ptr::write_volatile(self, T::default());
+        }
+    }
+}
+
+/// Buffer whose capacity is determined at compile time via the const
+/// generic `N`, defaulting to 512 elements.
+pub struct StaticBuffer<T, const N: usize = BUFFER_SIZE>
 where
     T: Copy + Default + Sized,
 {
-    elements: [T; BUFFER_SIZE],
+    elements: [T; N],
     next: usize,
 }
 
-impl<T> StaticBuffer<T>
+impl<T, const N: usize> StaticBuffer<T, N>
 where
     T: Copy + Default,
 {
-    /// Constructs a new buffer.
-    pub fn new() -> StaticBuffer<T> {
+    /// Constructs a new buffer with capacity `N`.
+    pub fn new() -> StaticBuffer<T, N> {
         StaticBuffer {
-            elements: [Default::default(); BUFFER_SIZE],
+            elements: [Default::default(); N],
+            next: 0,
+        }
+    }
+
+    /// Consumes the buffer like [`Buffer::consume`], then overwrites the
+    /// reclaimed elements with their default value so secrets (e.g. auth
+    /// tokens) don't linger in the backing array after they're read out.
+    pub fn consume_and_wipe(&mut self) -> Option<Vec<T>> {
+        let used = self.next;
+        let data = self.consume();
+        for element in &mut self.elements[..used] {
+            element.zeroize();
+        }
+        data
+    }
+
+    /// Copies as many elements from `src` as fit in the remaining capacity
+    /// in one `copy_from_slice`, returning how many were accepted.
+    ///
+    /// This is the batch counterpart to [`Buffer::add`], for feeding the
+    /// buffer from a reader or socket instead of appending one element at a
+    /// time.
+    pub fn extend_from_slice(&mut self, src: &[T]) -> Result<usize, BufferFullError<()>> {
+        if src.is_empty() {
+            return Ok(0);
+        }
+        if self.next == N {
+            return Err(BufferFullError(()));
+        }
+        let accepted = src.len().min(N - self.next);
+        self.elements[self.next..self.next + accepted].copy_from_slice(&src[..accepted]);
+        self.next += accepted;
+        Ok(accepted)
+    }
+}
+
+impl<const N: usize> std::io::Write for StaticBuffer<u8, N> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.extend_from_slice(buf)
+            .map_err(|_| std::io::ErrorKind::WouldBlock.into())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<T, const N: usize> Drop for StaticBuffer<T, N>
+where
+    T: Copy + Default,
+{
+    fn drop(&mut self) {
+        for element in &mut self.elements[..] {
+            element.zeroize();
+        }
+    }
+}
+
+impl<T, const N: usize> Buffer<T> for StaticBuffer<T, N>
+where
+    T: Copy + Default,
+{
+    fn add(&mut self, element: T) -> Result<(), BufferFullError<T>> {
+        if self.next == N {
+            return Err(BufferFullError(element));
+        }
+        self.elements[self.next] = element;
+        self.next += 1;
+        Ok(())
+    }
+
+    fn try_consume(&mut self) -> Result<Option<Vec<T>>, BufferAllocError> {
+        if self.next == 0 {
+            return Ok(None);
+        }
+        let mut data = Vec::new();
+        data.try_reserve_exact(self.next)?;
+        data.resize(self.next, T::default());
+        data.copy_from_slice(&self.elements[..self.next]);
+        self.next = 0;
+        Ok(Some(data))
+    }
+
+    fn used(&self) -> usize {
+        self.next
+    }
+
+    fn capacity(&self) -> usize {
+        N
+    }
+}
+
+/// Buffer whose capacity is resolved once at construction time instead of
+/// being fixed by a type parameter, e.g. from [`default_buffer_size`].
+pub struct DynamicBuffer<T>
+where
+    T: Copy + Default + Sized,
+{
+    elements: Box<[T]>,
+    next: usize,
+}
+
+impl<T> DynamicBuffer<T>
+where
+    T: Copy + Default,
+{
+    /// Constructs a new buffer with the given capacity.
+    pub fn with_capacity(capacity: usize) -> DynamicBuffer<T> {
+        DynamicBuffer {
+            elements: vec![Default::default(); capacity].into_boxed_slice(),
             next: 0,
         }
     }
+
+    /// Constructs a buffer sized from [`default_buffer_size`], so its
+    /// capacity can be tuned at runtime via `DROPRATE_BUFFER_SIZE` without a
+    /// recompile.
+    pub fn with_default_capacity() -> DynamicBuffer<T> {
+        DynamicBuffer::with_capacity(default_buffer_size())
+    }
 }
 
-impl<T> Buffer<T> for StaticBuffer<T>
+impl<T> Buffer<T> for DynamicBuffer<T>
 where
     T: Copy + Default,
 {
     fn add(&mut self, element: T) -> Result<(), BufferFullError<T>> {
-        if self.next == BUFFER_SIZE {
+        if self.next == self.elements.len() {
             return Err(BufferFullError(element));
         }
         self.elements[self.next] = element;
@@ -63,14 +255,16 @@ where
         Ok(())
     }
 
-    fn consume(&mut self) -> Option<Vec<T>> {
+    fn try_consume(&mut self) -> Result<Option<Vec<T>>, BufferAllocError> {
         if self.next == 0 {
-            return None;
+            return Ok(None);
         }
-        let mut data = vec![Default::default(); self.next];
+        let mut data = Vec::new();
+        data.try_reserve_exact(self.next)?;
+        data.resize(self.next, T::default());
         data.copy_from_slice(&self.elements[..self.next]);
         self.next = 0;
-        Some(data)
+        Ok(Some(data))
     }
 
     fn used(&self) -> usize {
@@ -78,7 +272,107 @@ where
     }
 
     fn capacity(&self) -> usize {
-        BUFFER_SIZE
+        self.elements.len()
+    }
+}
+
+/// Buffer that reclaims space as it is read, instead of requiring a full
+/// drain before more data can be added.
+///
+/// Capacity is rounded up to a power of two so the wrap-around index can be
+/// computed with a mask instead of a modulo.
+pub struct RingBuffer<T>
+where
+    T: Copy + Default + Sized,
+{
+    array: Box<[T]>,
+    head: usize,
+    length: usize,
+}
+
+impl<T> RingBuffer<T>
+where
+    T: Copy + Default,
+{
+    /// Constructs a new ring buffer with room for at least `capacity`
+    /// elements.
+    pub fn new(capacity: usize) -> RingBuffer<T> {
+        let capacity = capacity.max(1).next_power_of_two();
+        RingBuffer {
+            array: vec![T::default(); capacity].into_boxed_slice(),
+            head: 0,
+            length: 0,
+        }
+    }
+
+    fn mask(&self) -> usize {
+        self.array.len() - 1
+    }
+
+    /// Copies exactly `n` elements out of the buffer starting at `head` into
+    /// `data` (handling the wrap across the end of the array as two slice
+    /// copies), then advances `head` and shrinks `length` by `n`.
+    ///
+    /// `n` must not exceed `length`; callers clamp it before calling. `data`
+    /// must already have room for `n` more elements; this is the
+    /// shared copy/advance step behind both [`RingBuffer::consume_n`] and
+    /// `Buffer::try_consume`, so the wrap-around arithmetic lives in one
+    /// place regardless of how the caller allocated `data`.
+    fn drain_into(&mut self, n: usize, data: &mut Vec<T>) {
+        let first = n.min(self.array.len() - self.head);
+        data.extend_from_slice(&self.array[self.head..self.head + first]);
+        if n > first {
+            data.extend_from_slice(&self.array[..n - first]);
+        }
+
+        self.head = (self.head + n) & self.mask();
+        self.length -= n;
+    }
+
+    /// Copies up to `n` elements out of the buffer starting at `head`,
+    /// advancing `head` and shrinking `length` by the amount actually read.
+    ///
+    /// Unlike [`Buffer::consume`], this does not require draining the whole
+    /// buffer, so producers and consumers can run at different rates.
+    pub fn consume_n(&mut self, n: usize) -> Vec<T> {
+        let n = n.min(self.length);
+        let mut data = Vec::with_capacity(n);
+        self.drain_into(n, &mut data);
+        data
+    }
+}
+
+impl<T> Buffer<T> for RingBuffer<T>
+where
+    T: Copy + Default,
+{
+    fn add(&mut self, element: T) -> Result<(), BufferFullError<T>> {
+        if self.length == self.array.len() {
+            return Err(BufferFullError(element));
+        }
+        let index = (self.head + self.length) & self.mask();
+        self.array[index] = element;
+        self.length += 1;
+        Ok(())
+    }
+
+    fn try_consume(&mut self) -> Result<Option<Vec<T>>, BufferAllocError> {
+        if self.length == 0 {
+            return Ok(None);
+        }
+        let n = self.length;
+        let mut data = Vec::new();
+        data.try_reserve_exact(n)?;
+        self.drain_into(n, &mut data);
+        Ok(Some(data))
+    }
+
+    fn used(&self) -> usize {
+        self.length
+    }
+
+    fn capacity(&self) -> usize {
+        self.array.len()
     }
 }
 
@@ -90,7 +384,7 @@ mod tests {
     fn static_buffer_empty() {
         let mut buffer: StaticBuffer<i32> = StaticBuffer::new();
         assert_eq!(buffer.used(), 0);
-        assert_eq!(buffer.capacity(), BUFFER_SIZE);
+        assert_eq!(buffer.capacity(), default_buffer_size());
         assert!(buffer.consume().is_none());
     }
 
@@ -109,25 +403,26 @@ mod tests {
     #[test]
     fn static_buffer_add_until_full() {
         let mut buffer: StaticBuffer<usize> = StaticBuffer::new();
+        let buffer_size = default_buffer_size();
 
         // Add until full, making sure buffer does not error.
-        for value in 0..BUFFER_SIZE {
+        for value in 0..buffer_size {
             assert!(buffer.add(value).is_ok());
         }
-        assert_eq!(buffer.used(), BUFFER_SIZE);
+        assert_eq!(buffer.used(), buffer_size);
 
         // Try to add when full, check added value is returned with error.
-        let expected = BUFFER_SIZE;
+        let expected = buffer_size;
         if let Err(BufferFullError(actual)) = buffer.add(expected) {
             assert_eq!(actual, expected);
         } else {
             panic!("should have returned error");
         }
-        assert_eq!(buffer.used(), BUFFER_SIZE);
+        assert_eq!(buffer.used(), buffer_size);
 
         // Now consume everything.
         let actual = buffer.consume().unwrap();
-        let expected: Vec<usize> = (0..BUFFER_SIZE).collect();
+        let expected: Vec<usize> = (0..buffer_size).collect();
         assert_eq!(actual, expected);
 
         // And finally check we can add again.
@@ -137,4 +432,129 @@ mod tests {
         let actual = buffer.consume().unwrap();
         assert_eq!(actual, vec![expected]);
     }
+
+    #[test]
+    fn static_buffer_const_generic_capacity() {
+        let mut buffer: StaticBuffer<usize, 4> = StaticBuffer::new();
+        assert_eq!(buffer.capacity(), 4);
+        for value in 0..4 {
+            assert!(buffer.add(value).is_ok());
+        }
+        assert!(buffer.add(4).is_err());
+    }
+
+    // `default_buffer_size` reads a process-wide environment variable, so
+    // the unset/valid/unparseable cases are exercised in one test instead of
+    // three: `cargo test` runs tests in parallel within the same process,
+    // and separate tests mutating `DROPRATE_BUFFER_SIZE` concurrently would
+    // race on each other's expected state.
+    #[test]
+    fn default_buffer_size_reads_env_var() {
+        std::env::remove_var(BUFFER_SIZE_ENV_VAR);
+        assert_eq!(default_buffer_size(), BUFFER_SIZE);
+
+        std::env::set_var(BUFFER_SIZE_ENV_VAR, "1024");
+        assert_eq!(default_buffer_size(), 1024);
+
+        std::env::set_var(BUFFER_SIZE_ENV_VAR, "not-a-number");
+        assert_eq!(default_buffer_size(), BUFFER_SIZE);
+
+        std::env::remove_var(BUFFER_SIZE_ENV_VAR);
+    }
+
+    #[test]
+    fn try_consume_matches_consume() {
+        let mut buffer: StaticBuffer<usize, 4> = StaticBuffer::new();
+        assert_eq!(buffer.try_consume().unwrap(), None);
+
+        buffer.add(1).unwrap();
+        buffer.add(2).unwrap();
+        assert_eq!(buffer.try_consume().unwrap(), Some(vec![1, 2]));
+        assert_eq!(buffer.used(), 0);
+    }
+
+    #[test]
+    fn consume_and_wipe_zeroes_reclaimed_elements() {
+        let mut buffer: StaticBuffer<usize, 4> = StaticBuffer::new();
+        buffer.add(42).unwrap();
+        buffer.add(7).unwrap();
+
+        let data = buffer.consume_and_wipe().unwrap();
+        assert_eq!(data, vec![42, 7]);
+        assert_eq!(buffer.elements[..2], [0, 0]);
+    }
+
+    #[test]
+    fn extend_from_slice_accepts_up_to_remaining_capacity() {
+        let mut buffer: StaticBuffer<u8, 4> = StaticBuffer::new();
+        assert_eq!(buffer.extend_from_slice(&[1, 2]).unwrap(), 2);
+        assert_eq!(buffer.extend_from_slice(&[3, 4, 5]).unwrap(), 2);
+        assert_eq!(buffer.consume().unwrap(), vec![1, 2, 3, 4]);
+
+        assert!(matches!(buffer.extend_from_slice(&[1]), Ok(1)));
+        assert!(buffer.extend_from_slice(&[]).is_ok());
+    }
+
+    #[test]
+    fn static_buffer_u8_implements_io_write() {
+        use std::io::Write;
+
+        let mut buffer: StaticBuffer<u8, 4> = StaticBuffer::new();
+        assert_eq!(buffer.write(&[1, 2, 3]).unwrap(), 3);
+        assert_eq!(buffer.write(&[4, 5]).unwrap(), 1);
+        buffer.flush().unwrap();
+
+        let err = buffer.write(&[6]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+
+        assert_eq!(buffer.consume().unwrap(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn dynamic_buffer_sized_from_capacity() {
+        let mut buffer: DynamicBuffer<usize> = DynamicBuffer::with_capacity(2);
+        assert_eq!(buffer.capacity(), 2);
+        buffer.add(1).unwrap();
+        buffer.add(2).unwrap();
+        assert!(buffer.add(3).is_err());
+        assert_eq!(buffer.consume().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn ring_buffer_rounds_capacity_to_power_of_two() {
+        let buffer: RingBuffer<u8> = RingBuffer::new(5);
+        assert_eq!(buffer.capacity(), 8);
+    }
+
+    #[test]
+    fn ring_buffer_wraps_around() {
+        let mut buffer: RingBuffer<usize> = RingBuffer::new(4);
+
+        for value in 0..4 {
+            buffer.add(value).unwrap();
+        }
+        assert!(buffer.add(4).is_err());
+
+        // Drain part of the buffer so the next fill wraps past the end of the
+        // array, exercising the wrap-around copy.
+        assert_eq!(buffer.consume_n(2), vec![0, 1]);
+        buffer.add(4).unwrap();
+        buffer.add(5).unwrap();
+        assert_eq!(buffer.used(), 4);
+
+        assert_eq!(buffer.consume().unwrap(), vec![2, 3, 4, 5]);
+        assert!(buffer.consume().is_none());
+    }
+
+    #[test]
+    fn ring_buffer_consume_n_partial() {
+        let mut buffer: RingBuffer<usize> = RingBuffer::new(4);
+        buffer.add(1).unwrap();
+        buffer.add(2).unwrap();
+        buffer.add(3).unwrap();
+
+        assert_eq!(buffer.consume_n(2), vec![1, 2]);
+        assert_eq!(buffer.used(), 1);
+        assert_eq!(buffer.consume().unwrap(), vec![3]);
+    }
 }
@@ -1,4 +1,5 @@
 mod buffer;
+mod spsc;
 
 use buffer::{Buffer, StaticBuffer};
 
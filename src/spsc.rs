@@ -0,0 +1,210 @@
+//! A lock-free single-producer/single-consumer channel.
+//!
+//! Unlike [`crate::buffer::Buffer`], which is single-threaded and accessed
+//! through `&mut self`, this lets one thread push while another pops
+//! concurrently, with no locks and no allocation after the channel is
+//! constructed.
+
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Pads a cursor onto its own cache line so the reader's `head` and the
+/// writer's `tail` don't bounce between cores through false sharing.
+#[repr(align(64))]
+struct CachePadded<T>(T);
+
+struct Shared<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    mask: usize,
+    head: CachePadded<AtomicUsize>,
+    tail: CachePadded<AtomicUsize>,
+}
+
+// SAFETY: access to each slot is handed off between the producer and
+// consumer through the `head`/`tail` atomics, so `Shared` can be shared
+// across threads as long as `T` itself is safe to send between them.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+/// The write half of an SPSC channel, created by [`channel`].
+///
+/// `Producer` is `Send` but deliberately not `Sync`: the relaxed read of
+/// `tail` followed by a later `Release` store is only sound with a single
+/// writer, so the type system must stop two threads from sharing one
+/// `Producer` the way it would a `&Producer<T>`.
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+    _not_sync: PhantomData<std::cell::Cell<()>>,
+}
+
+/// The read half of an SPSC channel, created by [`channel`].
+///
+/// `Consumer` is `Send` but deliberately not `Sync`, for the same reason as
+/// [`Producer`].
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+    _not_sync: PhantomData<std::cell::Cell<()>>,
+}
+
+/// Creates a single-producer/single-consumer channel backed by one
+/// power-of-two ring allocation shared between the two returned handles.
+pub fn channel<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    let capacity = capacity.max(1).next_power_of_two();
+    let slots = (0..capacity)
+        .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+        .collect::<Vec<_>>()
+        .into_boxed_slice();
+    let shared = Arc::new(Shared {
+        slots,
+        mask: capacity - 1,
+        head: CachePadded(AtomicUsize::new(0)),
+        tail: CachePadded(AtomicUsize::new(0)),
+    });
+    (
+        Producer {
+            shared: shared.clone(),
+            _not_sync: PhantomData,
+        },
+        Consumer {
+            shared,
+            _not_sync: PhantomData,
+        },
+    )
+}
+
+impl<T> Producer<T> {
+    /// Pushes `value` onto the channel, handing it back if the ring is full.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let tail = self.shared.tail.0.load(Ordering::Relaxed);
+        let head = self.shared.head.0.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) >= self.shared.slots.len() {
+            return Err(value);
+        }
+
+        let slot = &self.shared.slots[tail & self.shared.mask];
+        // SAFETY: the consumer cannot reach this slot until `tail` is
+        // published below, so we have exclusive access to it.
+        unsafe {
+            (*slot.get()).write(value);
+        }
+        self.shared.tail.0.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+}
+
+impl<T> Consumer<T> {
+    /// Pops the oldest value off the channel, or `None` if it's empty.
+    pub fn pop(&self) -> Option<T> {
+        let head = self.shared.head.0.load(Ordering::Relaxed);
+        let tail = self.shared.tail.0.load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+
+        let slot = &self.shared.slots[head & self.shared.mask];
+        // SAFETY: `head != tail` means the producer has published this slot,
+        // and it won't be written again until we advance `head` below.
+        let value = unsafe { (*slot.get()).assume_init_read() };
+        self.shared.head.0.store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        // Only the last handle's drop of `Shared` reaches here, so this is
+        // the one place responsible for dropping any elements that were
+        // pushed but never popped.
+        let head = *self.head.0.get_mut();
+        let tail = *self.tail.0.get_mut();
+        let mut cursor = head;
+        while cursor != tail {
+            let slot = &mut self.slots[cursor & self.mask];
+            // SAFETY: every index between `head` and `tail` holds a value
+            // that was written by `push` and not yet read by `pop`.
+            unsafe {
+                slot.get_mut().assume_init_drop();
+            }
+            cursor = cursor.wrapping_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_rounds_capacity_to_power_of_two() {
+        let (producer, _consumer) = channel::<u8>(5);
+        assert_eq!(producer.shared.slots.len(), 8);
+    }
+
+    #[test]
+    fn push_then_pop_in_order() {
+        let (producer, consumer) = channel::<usize>(4);
+        assert!(consumer.pop().is_none());
+
+        producer.push(1).unwrap();
+        producer.push(2).unwrap();
+        assert_eq!(consumer.pop(), Some(1));
+        assert_eq!(consumer.pop(), Some(2));
+        assert!(consumer.pop().is_none());
+    }
+
+    #[test]
+    fn push_fails_when_full() {
+        let (producer, _consumer) = channel::<usize>(2);
+        producer.push(1).unwrap();
+        producer.push(2).unwrap();
+        assert_eq!(producer.push(3), Err(3));
+    }
+
+    #[test]
+    fn drop_frees_unread_elements() {
+        use std::sync::atomic::AtomicUsize as Counter;
+        use std::sync::Arc as Rc;
+
+        #[derive(Debug)]
+        struct DropCounter(Rc<Counter>);
+        impl Drop for DropCounter {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let drops = Rc::new(Counter::new(0));
+        let (producer, consumer) = channel::<DropCounter>(4);
+        producer.push(DropCounter(drops.clone())).unwrap();
+        producer.push(DropCounter(drops.clone())).unwrap();
+        consumer.pop();
+
+        drop(producer);
+        drop(consumer);
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn cross_thread_producer_consumer() {
+        let (producer, consumer) = channel::<usize>(16);
+        let handle = std::thread::spawn(move || {
+            let mut received = Vec::new();
+            while received.len() < 100 {
+                if let Some(value) = consumer.pop() {
+                    received.push(value);
+                }
+            }
+            received
+        });
+
+        for value in 0..100 {
+            while producer.push(value).is_err() {
+                std::thread::yield_now();
+            }
+        }
+
+        assert_eq!(handle.join().unwrap(), (0..100).collect::<Vec<_>>());
+    }
+}